@@ -1,136 +1,728 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use csv::ReaderBuilder;
+use rusqlite::types::Null;
 use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "csv_to_sqlite")]
-#[command(about = "Convert CSV files to SQLite database")]
-struct Args {
-    #[arg(help = "Input CSV file path")]
-    input: PathBuf,
-    
-    #[arg(help = "Output SQLite database path")]
+#[command(about = "Convert CSV files to SQLite databases and query them back out")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert CSV file(s) into a SQLite database
+    Convert(ConvertArgs),
+    /// Run SQL against a SQLite database and export the results as CSV
+    Query(QueryArgs),
+}
+
+#[derive(clap::Args)]
+struct ConvertArgs {
+    #[arg(required = true, help = "Input CSV file path(s); each becomes its own table")]
+    input: Vec<PathBuf>,
+
+    #[arg(short, long, help = "Output SQLite database path")]
     output: PathBuf,
-    
-    #[arg(short, long, default_value = "data", help = "Table name in the database")]
-    table: String,
-    
+
+    #[arg(short, long, help = "Table name in the database (only valid for a single input file; defaults to each file's stem)")]
+    table: Option<String>,
+
     #[arg(long, help = "Automatically infer column types")]
     infer_types: bool,
+
+    #[arg(
+        long,
+        default_value_t = 10_000,
+        help = "Number of rows to buffer per insert transaction (also the type-inference sample size)"
+    )]
+    batch_size: usize,
+
+    #[arg(long, default_value_t = ',', help = "Field delimiter character")]
+    delimiter: char,
+
+    #[arg(long, help = "Treat the CSV as headerless; columns are named col1..colN")]
+    no_headers: bool,
+
+    #[arg(long, default_value_t = '"', help = "Quote character")]
+    quote: char,
+
+    #[arg(long, help = "Comment character; lines starting with it are skipped")]
+    comment: Option<char>,
+
+    #[arg(
+        long = "column-type",
+        value_name = "NAME:TYPE",
+        help = "Override the inferred/default type for a named column (repeatable)"
+    )]
+    column_type: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "SQL",
+        help = "Instead of a plain import, register the CSV as a SQLite virtual table named \
+                `csv_input` and populate the output table from this SELECT against it"
+    )]
+    select: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = IfExists::Fail,
+        help = "Behavior when the target table already exists"
+    )]
+    if_exists: IfExists,
+
+    #[arg(long, help = "Column to declare PRIMARY KEY in the CREATE TABLE statement")]
+    primary_key: Option<String>,
+}
+
+/// What to do when the target table already exists: error out (the historical behavior),
+/// insert into it after checking the column sets line up, or drop and recreate it.
+#[derive(Clone, Copy, ValueEnum)]
+enum IfExists {
+    Fail,
+    Append,
+    Replace,
+}
+
+#[derive(clap::Args)]
+struct QueryArgs {
+    #[arg(help = "SQLite database path")]
+    database: PathBuf,
+
+    #[arg(help = "SQL query to run")]
+    sql: String,
+
+    #[arg(long, help = "Write the CSV output to a file instead of stdout")]
+    output: Option<PathBuf>,
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Strip an explicit hex-blob marker (`x'...'`/`X'...'` or `0x`/`0X`) and return the inner hex
+/// digits, or `None` if the value doesn't carry one of these markers. Requiring an explicit
+/// marker (rather than just "looks like hex") keeps ordinary words like `cafe` or `dead` from
+/// being mistaken for binary data.
+fn strip_hex_prefix(value: &str) -> Option<&str> {
+    let trimmed = value.trim();
+    if let Some(rest) = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")) {
+        return Some(rest);
+    }
+    trimmed
+        .strip_prefix('x')
+        .or_else(|| trimmed.strip_prefix('X'))
+        .and_then(|rest| rest.strip_prefix('\''))
+        .and_then(|rest| rest.strip_suffix('\''))
+}
+
+fn is_hex_blob(value: &str) -> bool {
+    match strip_hex_prefix(value) {
+        Some(hex) => !hex.is_empty() && hex.len().is_multiple_of(2) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+fn is_boolean(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "true" | "false" | "0" | "1" | "yes" | "no"
+    )
 }
 
 fn infer_column_type(values: &[String]) -> String {
     let mut int_count = 0;
     let mut float_count = 0;
+    let mut bool_count = 0;
+    let mut hex_count = 0;
     let mut empty_count = 0;
-    
+
     for value in values {
         if value.trim().is_empty() {
             empty_count += 1;
             continue;
         }
-        
+
         if value.parse::<i64>().is_ok() {
             int_count += 1;
         } else if value.parse::<f64>().is_ok() {
             float_count += 1;
         }
+
+        if is_boolean(value) {
+            bool_count += 1;
+        }
+        if is_hex_blob(value) {
+            hex_count += 1;
+        }
     }
-    
+
     let non_empty = values.len() - empty_count;
     if non_empty == 0 {
         return "TEXT".to_string();
     }
-    
-    if int_count as f64 / non_empty as f64 > 0.8 {
+
+    if bool_count as f64 / non_empty as f64 > 0.8 {
+        "BOOLEAN".to_string()
+    } else if int_count as f64 / non_empty as f64 > 0.8 {
         "INTEGER".to_string()
     } else if (int_count + float_count) as f64 / non_empty as f64 > 0.8 {
         "REAL".to_string()
+    } else if hex_count as f64 / non_empty as f64 > 0.8 {
+        "BLOB".to_string()
     } else {
         "TEXT".to_string()
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    
-    println!("Reading CSV file: {}", args.input.display());
-    
-    // Read CSV file
-    let file = File::open(&args.input)?;
-    let mut rdr = ReaderBuilder::new().from_reader(file);
-    
-    let headers = rdr.headers()?.clone();
-    let mut records: Vec<Vec<String>> = Vec::new();
-    
-    for result in rdr.records() {
-        let record = result?;
-        records.push(record.iter().map(|s| s.to_string()).collect());
-    }
-    
-    println!("Found {} columns and {} rows", headers.len(), records.len());
-    
-    // Determine column types
-    let column_types: Vec<String> = if args.infer_types {
-        println!("Inferring column types...");
-        headers.iter().enumerate().map(|(i, _)| {
-            let column_values: Vec<String> = records.iter()
-                .filter_map(|row| row.get(i).cloned())
-                .collect();
-            infer_column_type(&column_values)
-        }).collect()
+/// Convert a raw CSV cell into the boxed `ToSql` value appropriate for `column_type`,
+/// mirroring nushell's `nu_value_to_sqlite_string` handling of empty cells as NULL.
+fn cell_to_sql(value: &str, column_type: &str) -> Box<dyn rusqlite::ToSql> {
+    if value.trim().is_empty() {
+        return Box::new(Null);
+    }
+
+    match column_type {
+        "INTEGER" => match value.parse::<i64>() {
+            Ok(i) => Box::new(i),
+            Err(_) => Box::new(value.to_string()),
+        },
+        "REAL" => match value.parse::<f64>() {
+            Ok(f) => Box::new(f),
+            Err(_) => Box::new(value.to_string()),
+        },
+        "BOOLEAN" => match value.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" => Box::new(1i64),
+            "false" | "0" | "no" => Box::new(0i64),
+            _ => Box::new(value.to_string()),
+        },
+        "BLOB" => match strip_hex_prefix(value).and_then(decode_hex) {
+            Some(bytes) => Box::new(bytes),
+            None => Box::new(value.to_string()),
+        },
+        _ => Box::new(value.to_string()),
+    }
+}
+
+/// Build a CSV reader configured with the dialect options (`--delimiter`, `--no-headers`,
+/// `--quote`, `--comment`) the user asked for.
+fn build_reader(path: &Path, args: &ConvertArgs) -> Result<csv::Reader<File>> {
+    let file = File::open(path)?;
+    let mut builder = ReaderBuilder::new();
+    builder
+        .delimiter(args.delimiter as u8)
+        .quote(args.quote as u8)
+        .has_headers(!args.no_headers);
+
+    if let Some(comment) = args.comment {
+        builder.comment(Some(comment as u8));
+    }
+
+    Ok(builder.from_reader(file))
+}
+
+/// Resolve the header row, synthesizing `col1..colN` when `--no-headers` is set. When headers
+/// are disabled, `csv` still hands back the first row here, and that same row is also yielded
+/// by `records()`, so it's correctly counted as a data row rather than dropped.
+fn resolve_headers(rdr: &mut csv::Reader<File>, no_headers: bool) -> Result<csv::StringRecord> {
+    let raw = rdr.headers()?.clone();
+    if no_headers {
+        Ok((1..=raw.len()).map(|i| format!("col{}", i)).collect())
     } else {
+        Ok(raw)
+    }
+}
+
+/// Parse repeated `--column-type name:TYPE` values into a lookup table.
+fn parse_column_type_overrides(overrides: &[String]) -> Result<HashMap<String, String>> {
+    overrides
+        .iter()
+        .map(|entry| {
+            let (name, col_type) = entry.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!("invalid --column-type '{}', expected NAME:TYPE", entry)
+            })?;
+            Ok((name.to_string(), col_type.to_uppercase()))
+        })
+        .collect()
+}
+
+/// Read up to `sample_size` rows from the start of the CSV and infer a type per column,
+/// then apply any `--column-type` overrides by header name.
+/// Inference is sample-based: a file larger than `sample_size` rows only has its head
+/// examined. Values further down that don't fit the inferred type are not lost:
+/// `cell_to_sql` binds any cell that doesn't convert to its column's type as TEXT.
+fn sample_column_types(
+    path: &Path,
+    headers: &csv::StringRecord,
+    args: &ConvertArgs,
+    overrides: &HashMap<String, String>,
+) -> Result<Vec<String>> {
+    let mut column_types = if !args.infer_types {
         vec!["TEXT".to_string(); headers.len()]
+    } else {
+        println!("Inferring column types from up to {} sample rows...", args.batch_size);
+        let mut rdr = build_reader(path, args)?;
+        resolve_headers(&mut rdr, args.no_headers)?;
+
+        let mut sample: Vec<Vec<String>> = Vec::new();
+        for result in rdr.records().take(args.batch_size) {
+            let record = result?;
+            sample.push(record.iter().map(|s| s.to_string()).collect());
+        }
+
+        (0..headers.len())
+            .map(|i| {
+                let column_values: Vec<String> = sample.iter()
+                    .filter_map(|row| row.get(i).cloned())
+                    .collect();
+                infer_column_type(&column_values)
+            })
+            .collect()
     };
-    
-    // Create SQLite database
-    println!("Creating SQLite database: {}", args.output.display());
-    let conn = Connection::open(&args.output)?;
-    
-    // Create table
+
+    for (i, header) in headers.iter().enumerate() {
+        if let Some(col_type) = overrides.get(header) {
+            column_types[i] = col_type.clone();
+        }
+    }
+
+    Ok(column_types)
+}
+
+/// Turn a file stem into a valid, unquoted SQLite identifier: non-alphanumeric characters
+/// become underscores, and a leading digit gets a `_` prefix since SQLite identifiers can't
+/// start with one.
+fn sanitize_identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    if ident.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+        ident.insert(0, '_');
+    }
+
+    if ident.is_empty() {
+        ident.push_str("table");
+    }
+
+    ident
+}
+
+/// Escape a value for embedding in a single-quoted SQL string literal.
+fn sql_quote(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+/// Register `path` as the `csv_input` virtual table via rusqlite's `csvtab` module, run
+/// `select_sql` against it, and materialize the result as `table` with `CREATE TABLE ... AS
+/// SELECT`. This reuses SQLite's own CSV reader, so it's a way to filter/rename/cast columns
+/// at import time without a separate ETL pass.
+fn ingest_via_select(conn: &Connection, path: &Path, table: &str, select_sql: &str, args: &ConvertArgs) -> Result<usize> {
+    if matches!(args.if_exists, IfExists::Append) {
+        anyhow::bail!(
+            "--if-exists append is not supported together with --select: \
+             `CREATE TABLE IF NOT EXISTS ... AS SELECT` silently does nothing against an \
+             existing table, so this would report success without appending any rows"
+        );
+    }
+
+    rusqlite::vtab::csvtab::load_module(conn)?;
+
+    let vtab_name = "csv_input";
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", vtab_name), [])?;
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE {} USING csv(filename='{}', delimiter='{}', header={})",
+            vtab_name,
+            sql_quote(&path.display().to_string()),
+            args.delimiter,
+            if args.no_headers { "no" } else { "yes" },
+        ),
+        [],
+    )?;
+
+    if matches!(args.if_exists, IfExists::Replace) {
+        conn.execute(&format!("DROP TABLE IF EXISTS \"{}\"", table), [])?;
+    }
+    conn.execute(&format!("CREATE TABLE \"{}\" AS {}", table, select_sql), [])?;
+    conn.execute(&format!("DROP TABLE {}", vtab_name), [])?;
+
+    let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+    println!("âœ… Table {} populated via --select, {} rows", table, rows);
+    Ok(rows as usize)
+}
+
+/// Check that an `--if-exists append` target's existing columns match the CSV headers,
+/// ignoring order, before any row gets inserted.
+fn check_append_columns(table: &str, existing: &HashSet<String>, incoming: &HashSet<String>) -> Result<()> {
+    if existing != incoming {
+        anyhow::bail!(
+            "--if-exists append: existing table \"{}\" has columns {:?}, but the CSV has {:?}",
+            table,
+            existing,
+            incoming
+        );
+    }
+    Ok(())
+}
+
+/// Fetch the current column names of `table` (empty if it doesn't exist) via `PRAGMA table_info`.
+fn existing_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{}\")", table))?;
+    let cols = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(cols)
+}
+
+/// Stream one CSV file into `table` within the already-open `conn`, sampling for type
+/// inference and inserting in batch-sized transactions.
+fn ingest_file(
+    conn: &mut Connection,
+    path: &Path,
+    table: &str,
+    args: &ConvertArgs,
+    overrides: &HashMap<String, String>,
+) -> Result<usize> {
+    println!("Reading CSV file: {}", path.display());
+
+    let mut rdr = build_reader(path, args)?;
+    let headers = resolve_headers(&mut rdr, args.no_headers)?;
+    let column_types = sample_column_types(path, &headers, args, overrides)?;
+    let batch_size = args.batch_size;
+
+    println!("Found {} columns", headers.len());
+
+    if matches!(args.if_exists, IfExists::Replace) {
+        conn.execute(&format!("DROP TABLE IF EXISTS \"{}\"", table), [])?;
+    }
+
+    let create_prefix = match args.if_exists {
+        IfExists::Append => "CREATE TABLE IF NOT EXISTS",
+        IfExists::Fail | IfExists::Replace => "CREATE TABLE",
+    };
+
     let create_sql = format!(
-        "CREATE TABLE {} ({})",
-        args.table,
+        "{} \"{}\" ({})",
+        create_prefix,
+        table,
         headers.iter().zip(column_types.iter())
-            .map(|(header, col_type)| format!("\"{}\" {}", header, col_type))
+            .map(|(header, col_type)| {
+                let pk = if args.primary_key.as_deref() == Some(header) { " PRIMARY KEY" } else { "" };
+                format!("\"{}\" {}{}", header, col_type, pk)
+            })
             .collect::<Vec<_>>()
             .join(", ")
     );
-    
+
     conn.execute(&create_sql, [])?;
-    println!("Created table: {}", args.table);
-    
-    // Insert data
+    println!("Created table: {}", table);
+
+    if matches!(args.if_exists, IfExists::Append) {
+        let existing: HashSet<String> = existing_columns(conn, table)?.into_iter().collect();
+        let incoming: HashSet<String> = headers.iter().map(|h| h.to_string()).collect();
+        check_append_columns(table, &existing, &incoming)?;
+    }
+
     let insert_sql = format!(
-        "INSERT INTO {} ({}) VALUES ({})",
-        args.table,
+        "INSERT INTO \"{}\" ({}) VALUES ({})",
+        table,
         headers.iter().map(|h| format!("\"{}\"", h)).collect::<Vec<_>>().join(", "),
         (0..headers.len()).map(|_| "?").collect::<Vec<_>>().join(", ")
     );
-    
+
+    // Stream the file a second time, inserting one batch-sized transaction at a time so
+    // a crash mid-import leaves a partially-populated but valid DB instead of losing everything.
+    let mut batch: Vec<Vec<String>> = Vec::with_capacity(batch_size);
+    let mut total_rows = 0usize;
+
+    for result in rdr.records() {
+        let record = result?;
+        batch.push(record.iter().map(|s| s.to_string()).collect());
+
+        if batch.len() >= batch_size {
+            total_rows += insert_batch(conn, &insert_sql, &batch, &column_types)?;
+            println!("Inserted {} rows...", total_rows);
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        total_rows += insert_batch(conn, &insert_sql, &batch, &column_types)?;
+    }
+
+    println!("âœ… Table {} done, {} rows inserted", table, total_rows);
+    Ok(total_rows)
+}
+
+/// Bail if two input paths derive (or were given) the same table name, rather than letting
+/// `--if-exists replace`/`append` silently drop or merge an earlier file's rows.
+fn check_table_name_collisions(inputs: &[PathBuf], tables: &[String]) -> Result<()> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for table in tables {
+        if !seen.insert(table.as_str()) {
+            anyhow::bail!(
+                "multiple input files resolve to the same table name \"{}\" ({:?}); \
+                 pass distinct filenames or rename the files so their stems don't collide",
+                table,
+                inputs
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_convert(args: &ConvertArgs) -> Result<()> {
+    if args.table.is_some() && args.input.len() > 1 {
+        anyhow::bail!("--table can only be used with a single input file; omit it to derive a table name per file");
+    }
+
+    let overrides = parse_column_type_overrides(&args.column_type)?;
+
+    let tables: Vec<String> = args.input.iter().map(|path| {
+        match &args.table {
+            Some(t) => t.clone(),
+            None => sanitize_identifier(path.file_stem().and_then(|s| s.to_str()).unwrap_or("table")),
+        }
+    }).collect();
+    check_table_name_collisions(&args.input, &tables)?;
+
+    println!("Creating SQLite database: {}", args.output.display());
+    let mut conn = Connection::open(&args.output)?;
+
+    let mut total_rows = 0usize;
+    for (path, table) in args.input.iter().zip(tables.iter()) {
+        total_rows += match &args.select {
+            Some(select_sql) => ingest_via_select(&conn, path, table, select_sql, args)?,
+            None => ingest_file(&mut conn, path, table, args, &overrides)?,
+        };
+    }
+
+    println!("âœ… Successfully converted {} CSV file(s) to SQLite!", args.input.len());
+    println!("ðŸ“Š Database: {}", args.output.display());
+    println!("ðŸ“ˆ Total rows inserted: {}", total_rows);
+
+    Ok(())
+}
+
+/// Convert a single result-row value into the text that goes in the exported CSV, writing
+/// blobs back out as the same `x'...'` hex form `--infer-types` accepts on the way in.
+fn sql_value_to_csv_field(value: rusqlite::types::ValueRef) -> String {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).to_string(),
+        ValueRef::Blob(b) => format!("x'{}'", encode_hex(b)),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn run_query(args: &QueryArgs) -> Result<()> {
+    let conn = Connection::open(&args.database)?;
+    let mut stmt = conn.prepare(&args.sql)?;
+    let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    let column_count = column_names.len();
+
+    let mut writer: csv::Writer<Box<dyn io::Write>> = match &args.output {
+        Some(path) => csv::Writer::from_writer(Box::new(File::create(path)?)),
+        None => csv::Writer::from_writer(Box::new(io::stdout())),
+    };
+
+    writer.write_record(&column_names)?;
+
+    let mut rows = stmt.query([])?;
+    let mut row_count = 0usize;
+    while let Some(row) = rows.next()? {
+        let record: Vec<String> = (0..column_count)
+            .map(|i| Ok(sql_value_to_csv_field(row.get_ref(i)?)))
+            .collect::<Result<_>>()?;
+        writer.write_record(&record)?;
+        row_count += 1;
+    }
+    writer.flush()?;
+
+    eprintln!("âœ… Wrote {} row(s) as CSV", row_count);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Convert(args) => run_convert(&args),
+        Command::Query(args) => run_query(&args),
+    }
+}
+
+/// Insert one batch inside its own transaction, committing before returning so a crash in a
+/// later batch doesn't roll back rows that already made it to disk.
+fn insert_batch(
+    conn: &mut Connection,
+    insert_sql: &str,
+    batch: &[Vec<String>],
+    column_types: &[String],
+) -> Result<usize> {
     let tx = conn.transaction()?;
-    let mut stmt = tx.prepare(&insert_sql)?;
-    
-    for (i, record) in records.iter().enumerate() {
-        let params: Vec<&dyn rusqlite::ToSql> = record.iter()
-            .map(|s| s as &dyn rusqlite::ToSql)
-            .collect();
-        
-        stmt.execute(&*params)?;
-        
-        if (i + 1) % 1000 == 0 {
-            println!("Inserted {} rows...", i + 1);
+    {
+        let mut stmt = tx.prepare(insert_sql)?;
+        for record in batch {
+            let params: Vec<Box<dyn rusqlite::ToSql>> = record.iter()
+                .zip(column_types.iter())
+                .map(|(value, col_type)| cell_to_sql(value, col_type))
+                .collect();
+            let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+            stmt.execute(&*param_refs)?;
         }
     }
-    
     tx.commit()?;
-    
-    println!("âœ… Successfully converted CSV to SQLite!");
-    println!("ðŸ“Š Table: {} in {}", args.table, args.output.display());
-    println!("ðŸ“ˆ Rows inserted: {}", records.len());
-    
-    Ok(())
+    Ok(batch.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::ToSql;
+
+    #[test]
+    fn infer_column_type_detects_integer() {
+        let values = vec!["1".to_string(), "2".to_string(), "3".to_string()];
+        assert_eq!(infer_column_type(&values), "INTEGER");
+    }
+
+    #[test]
+    fn infer_column_type_detects_real() {
+        let values = vec!["1.5".to_string(), "2".to_string(), "3.25".to_string()];
+        assert_eq!(infer_column_type(&values), "REAL");
+    }
+
+    #[test]
+    fn infer_column_type_detects_boolean() {
+        let values = vec!["true".to_string(), "false".to_string(), "yes".to_string()];
+        assert_eq!(infer_column_type(&values), "BOOLEAN");
+    }
+
+    #[test]
+    fn infer_column_type_does_not_mistake_plain_hex_words_for_blob() {
+        let values = vec!["cafe".to_string(), "dead".to_string(), "face".to_string(), "beef".to_string()];
+        assert_eq!(infer_column_type(&values), "TEXT");
+    }
+
+    #[test]
+    fn infer_column_type_detects_explicit_hex_blob() {
+        let values = vec!["x'CAFE'".to_string(), "0xDEAD".to_string(), "x'BEEF'".to_string()];
+        assert_eq!(infer_column_type(&values), "BLOB");
+    }
+
+    #[test]
+    fn infer_column_type_falls_back_to_text() {
+        let values = vec!["hello".to_string(), "world".to_string()];
+        assert_eq!(infer_column_type(&values), "TEXT");
+    }
+
+    #[test]
+    fn decode_hex_round_trips() {
+        assert_eq!(decode_hex("cafe"), Some(vec![0xca, 0xfe]));
+        assert_eq!(decode_hex("CAFE"), Some(vec![0xca, 0xfe]));
+    }
+
+    #[test]
+    fn decode_hex_rejects_odd_length() {
+        assert_eq!(decode_hex("abc"), None);
+    }
+
+    #[test]
+    fn decode_hex_rejects_non_hex_digits() {
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn decode_hex_rejects_non_ascii_instead_of_panicking() {
+        // "héa" is 4 bytes (h, a 2-byte é, a), so it passes the even-length check; slicing on
+        // byte offset 2 would land inside the 'é' and panic if we didn't bail out on the
+        // is_ascii() check first.
+        assert_eq!(decode_hex("héa"), None);
+    }
+
+    #[test]
+    fn is_hex_blob_requires_explicit_marker() {
+        assert!(!is_hex_blob("cafe"));
+        assert!(!is_hex_blob("dead"));
+        assert!(is_hex_blob("x'cafe'"));
+        assert!(is_hex_blob("0xCAFE"));
+    }
+
+    #[test]
+    fn sanitize_identifier_replaces_invalid_characters() {
+        assert_eq!(sanitize_identifier("my-data.csv"), "my_data_csv");
+    }
+
+    #[test]
+    fn sanitize_identifier_prefixes_leading_digit() {
+        assert_eq!(sanitize_identifier("2024_sales"), "_2024_sales");
+    }
+
+    #[test]
+    fn check_append_columns_rejects_mismatched_sets() {
+        let existing: HashSet<String> = ["a".to_string(), "b".to_string()].into_iter().collect();
+        let incoming: HashSet<String> = ["a".to_string(), "c".to_string()].into_iter().collect();
+        assert!(check_append_columns("t", &existing, &incoming).is_err());
+    }
+
+    #[test]
+    fn check_append_columns_allows_matching_sets_regardless_of_order() {
+        let existing: HashSet<String> = ["a".to_string(), "b".to_string()].into_iter().collect();
+        let incoming: HashSet<String> = ["b".to_string(), "a".to_string()].into_iter().collect();
+        assert!(check_append_columns("t", &existing, &incoming).is_ok());
+    }
+
+    fn blob_bytes(value: &str) -> Vec<u8> {
+        match cell_to_sql(value, "BLOB").to_sql().unwrap() {
+            rusqlite::types::ToSqlOutput::Owned(rusqlite::types::Value::Blob(bytes)) => bytes,
+            other => panic!("expected a decoded blob, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cell_to_sql_decodes_all_four_hex_blob_marker_forms() {
+        assert_eq!(blob_bytes("x'DEAD'"), vec![0xde, 0xad]);
+        assert_eq!(blob_bytes("X'DEAD'"), vec![0xde, 0xad]);
+        assert_eq!(blob_bytes("0xDEAD"), vec![0xde, 0xad]);
+        assert_eq!(blob_bytes("0XDEAD"), vec![0xde, 0xad]);
+    }
+
+    #[test]
+    fn check_table_name_collisions_rejects_duplicate_stems() {
+        let inputs = vec![PathBuf::from("a/data.csv"), PathBuf::from("b/data.csv")];
+        let tables = vec!["data".to_string(), "data".to_string()];
+        assert!(check_table_name_collisions(&inputs, &tables).is_err());
+    }
+
+    #[test]
+    fn check_table_name_collisions_allows_distinct_names() {
+        let inputs = vec![PathBuf::from("a/data.csv"), PathBuf::from("b/other.csv")];
+        let tables = vec!["data".to_string(), "other".to_string()];
+        assert!(check_table_name_collisions(&inputs, &tables).is_ok());
+    }
 }
\ No newline at end of file